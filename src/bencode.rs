@@ -1,8 +1,12 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::num::ParseIntError;
 use std::str::from_utf8;
+use std::string::FromUtf8Error;
 
 use nom::{
     IResult,
+    error::{ErrorKind, ParseError, FromExternalError},
     character::complete::{char, digit1},
     combinator::{map_res, opt, recognize, map},
     sequence::{preceded, terminated, pair},
@@ -12,12 +16,133 @@ use nom::{
 };
 
 
+/// Error produced while parsing or decoding Bencode data
+///
+/// Bencode input may come from untrusted peers, so parsing never panics: every failure surfaces
+/// as one of these variants instead.
+#[derive(Debug, PartialEq)]
+pub enum BencodeError {
+    /// Input ended before a value could be fully parsed
+    InputTooShort,
+    /// A value was not of the kind required: a non-digit where a number was expected while
+    /// parsing, a value of the wrong variant, or a missing dictionary entry (accessors, decoders,
+    /// `info_encoded`). Input whose first byte starts no known value type is reported as
+    /// `Expected('i')` instead
+    UnknownType,
+    /// A parsed integer or byte-string length was not a valid `i64`/`usize`
+    InvalidNumber(ParseIntError),
+    /// Bytes expected to be UTF-8 (e.g. by `BValue::get_string`) were not
+    InvalidUtf8(FromUtf8Error),
+    /// A literal character (`i`, `e`, `:`, `l`, `d`) was expected but not found
+    Expected(char),
+    /// An integer or byte-string length had a leading zero, or was `-0` (only checked in strict mode)
+    LeadingZero,
+    /// Dictionary keys were not strictly increasing, raw-byte lexicographic order (only checked in strict mode)
+    UnsortedKeys,
+}
+
+
+impl fmt::Display for BencodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BencodeError::InputTooShort => write!(f, "input ended unexpectedly"),
+            BencodeError::UnknownType => write!(f, "unrecognized bencode value type"),
+            BencodeError::InvalidNumber(e) => write!(f, "invalid number: {}", e),
+            BencodeError::InvalidUtf8(e) => write!(f, "invalid utf-8: {}", e),
+            BencodeError::Expected(c) => write!(f, "expected '{}'", c),
+            BencodeError::LeadingZero => write!(f, "non-canonical leading zero"),
+            BencodeError::UnsortedKeys => write!(f, "dictionary keys are not strictly sorted"),
+        }
+    }
+}
+
+
+impl std::error::Error for BencodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BencodeError::InvalidNumber(e) => Some(e),
+            BencodeError::InvalidUtf8(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+
+/// Internal parse error that additionally remembers how much input was left when it occurred
+///
+/// `alt` tries each `BValue` variant's parser in turn and combines their errors via
+/// `ParseError::or`; without tracking position, the default `or` (or any heuristic keyed only
+/// on the error variant) ends up preferring whichever branch happened to run last rather than
+/// whichever branch actually matched furthest into the input. Tracking the remaining slice lets
+/// `or` keep the error from the branch that got closest to succeeding, which is the standard nom
+/// approach. This type never escapes the parser: `from_bytes`/`from_bytes_strict` unwrap it down
+/// to the public, position-less `BencodeError` before returning.
+#[derive(Debug)]
+struct PosError<'a> {
+    error: BencodeError,
+    remaining: &'a [u8],
+}
+
+
+impl<'a> PosError<'a> {
+    fn new(error: BencodeError, remaining: &'a [u8]) -> Self {
+        PosError { error, remaining }
+    }
+}
+
+
+impl<'a> ParseError<&'a [u8]> for PosError<'a> {
+    fn from_error_kind(input: &'a [u8], _kind: ErrorKind) -> Self {
+        let error = if input.is_empty() { BencodeError::InputTooShort } else { BencodeError::UnknownType };
+        PosError::new(error, input)
+    }
+
+    fn append(_input: &'a [u8], _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        // Keep whichever error has the shorter remainder, i.e. whichever branch consumed more
+        // input before failing.
+        if other.remaining.len() != self.remaining.len() {
+            return if other.remaining.len() < self.remaining.len() { other } else { self };
+        }
+
+        // Tied on how far they got: if either ran out of input entirely, that's a clearer
+        // root cause than "expected this specific character", which is just an artifact of
+        // which `BValue` variant's tag check happened to run first.
+        match (&self.error, &other.error) {
+            (BencodeError::InputTooShort, _) => self,
+            (_, BencodeError::InputTooShort) => other,
+            _ => self,
+        }
+    }
+}
+
+
+impl<'a> FromExternalError<&'a [u8], ParseIntError> for PosError<'a> {
+    fn from_external_error(input: &'a [u8], _kind: ErrorKind, e: ParseIntError) -> Self {
+        PosError::new(BencodeError::InvalidNumber(e), input)
+    }
+}
+
+
+impl<'a> FromExternalError<&'a [u8], FromUtf8Error> for PosError<'a> {
+    fn from_external_error(input: &'a [u8], _kind: ErrorKind, e: FromUtf8Error) -> Self {
+        PosError::new(BencodeError::InvalidUtf8(e), input)
+    }
+}
+
+
 /// Bencode value representation class (ADT)
 /// ## Constructors
 /// You can parse byte array (`&[u8]`) of Bencoded object using static `from_bytes` fabric
 /// method.
-/// 
+///
 /// You can parse string of Bencoded object using static `from_string` fabric method.
+///
+/// `from_bytes_strict`/`from_string_strict` parse the same grammar but additionally reject
+/// non-canonical encodings (leading zeros, `-0`, unsorted dictionary keys).
 /// ## Methods
 /// You can convert `BValue` to any of variants using methods:
 /// - `get_number` -> `BNumber`
@@ -33,13 +158,15 @@ use nom::{
 /// ### `BList`
 /// Vector with some `BValue` elements.
 /// ### `BDict`
-/// `HashMap` with string keys and `BValue` values.
-#[derive(Debug,PartialEq)]
+/// `BTreeMap` with raw byte-string keys (not necessarily valid UTF-8) and `BValue` values. Using
+/// a sorted map means re-encoding a dict always produces canonical (lexicographically sorted)
+/// key order.
+#[derive(Debug,PartialEq,Clone)]
 pub enum BValue {
     BNumber(i64),
     BBytes(Vec<u8>),
     BList(Vec<BValue>),
-    BDict(HashMap<String, BValue>),
+    BDict(BTreeMap<Vec<u8>, BValue>),
 }
 
 
@@ -52,12 +179,11 @@ impl BValue {
     /// use torcode::bencode::BValue;
     /// assert_eq!(BValue::from_bytes(&b"i3e"[..]), Ok((&b""[..], BValue::BNumber(3))));
     /// ```
-    pub fn from_bytes(i: &[u8]) -> IResult<&[u8], BValue> {
-        let bnumber = map(parse_number, BValue::BNumber);
-        let bbytes = map(parse_bytes, BValue::BBytes);
-        let blist = map(parse_list, BValue::BList);
-        let bdict = map(parse_dict, BValue::BDict);
-        alt((bnumber, bbytes, blist, bdict))(i)
+    pub fn from_bytes(i: &[u8]) -> Result<(&[u8], BValue), BencodeError> {
+        parse_value(i).map_err(|e| match e {
+            nom::Err::Incomplete(_) => BencodeError::InputTooShort,
+            nom::Err::Error(err) | nom::Err::Failure(err) => err.error,
+        })
     }
 
 
@@ -69,11 +195,59 @@ impl BValue {
     /// use torcode::bencode::BValue;
     /// assert_eq!(BValue::from_string("i3228e"), Ok((&b""[..], BValue::BNumber(3228))));
     /// ```
-    pub fn from_string(s: &str) -> IResult<&[u8], BValue> {
+    pub fn from_string(s: &str) -> Result<(&[u8], BValue), BencodeError> {
         BValue::from_bytes(s.as_bytes())
     }
 
 
+    /// Parse array of Bencode bytes to `BValue`, rejecting non-canonical encodings
+    ///
+    /// On top of everything `from_bytes` checks, this also rejects leading zeros and `-0` in
+    /// integers and byte-string lengths, and requires dictionary keys to be in strictly
+    /// increasing, raw-byte lexicographic order with no duplicates. This is the form required
+    /// to reproduce the exact bytes a BitTorrent info-dict hash is computed over.
+    /// ## Arguments
+    /// - `i` bytes of bencode object
+    /// ## Example
+    /// ```rust
+    /// use torcode::bencode::{BValue, BencodeError};
+    /// assert_eq!(BValue::from_bytes_strict(&b"i03e"[..]), Err(BencodeError::LeadingZero));
+    /// ```
+    pub fn from_bytes_strict(i: &[u8]) -> Result<(&[u8], BValue), BencodeError> {
+        parse_value_strict(i).map_err(|e| match e {
+            nom::Err::Incomplete(_) => BencodeError::InputTooShort,
+            nom::Err::Error(err) | nom::Err::Failure(err) => err.error,
+        })
+    }
+
+
+    /// Parse string of Bencode to `BValue`, rejecting non-canonical encodings
+    /// ## Arguments
+    /// - `s` string, which represent Bencoded object to parse
+    pub fn from_string_strict(s: &str) -> Result<(&[u8], BValue), BencodeError> {
+        BValue::from_bytes_strict(s.as_bytes())
+    }
+
+
+    /// Lazily parses a stream of concatenated Bencode values, such as a run of tracker
+    /// responses piped together
+    ///
+    /// Each step feeds the slice left over by the previous `from_bytes` call, so nothing is
+    /// read ahead past the current value's terminator. Iteration stops once the remaining input
+    /// is empty; a parse error is yielded once and ends the iterator.
+    /// ## Arguments
+    /// - `i` bytes containing zero or more concatenated bencode objects
+    /// ## Example
+    /// ```rust
+    /// use torcode::bencode::BValue;
+    /// let values: Vec<_> = BValue::iter_from_bytes(&b"i1ei2e"[..]).collect();
+    /// assert_eq!(values, vec![Ok(BValue::BNumber(1)), Ok(BValue::BNumber(2))]);
+    /// ```
+    pub fn iter_from_bytes(i: &[u8]) -> impl Iterator<Item = Result<BValue, BencodeError>> + '_ {
+        ValueStream { remaining: i, done: false }
+    }
+
+
     /// Returns `i64` value if object has `BNumber` type, else `None`
     pub fn get_number(&self) -> Option<&i64> {
         match self {
@@ -101,8 +275,8 @@ impl BValue {
     }
 
 
-    /// Returns dictionary `String`->`BValue` if object has `BDict` type, else `None`
-    pub fn get_dict(&self) -> Option<&HashMap<String, BValue>> {
+    /// Returns dictionary raw-bytes->`BValue` map if object has `BDict` type, else `None`
+    pub fn get_dict(&self) -> Option<&BTreeMap<Vec<u8>, BValue>> {
         match self {
             BValue::BDict(map) => Some(map),
             _ => None
@@ -110,51 +284,260 @@ impl BValue {
     }
 
 
-    /// Returns string representation of ASCII bytes if object has `BBytes` type, else `None`
-    pub fn get_string(&self) -> Option<&str> {
+    /// Looks up `key` in this value's dictionary, if it has `BDict` type, else `None`
+    pub fn get(&self, key: &[u8]) -> Option<&BValue> {
+        self.get_dict()?.get(key)
+    }
+
+
+    /// Returns string representation of UTF-8 bytes if object has `BBytes` type, or an error if
+    /// the value is not a byte string or is not valid UTF-8
+    pub fn get_string(&self) -> Result<&str, BencodeError> {
         match self.get_bytes() {
-            Some(bytes) => Some(from_utf8(bytes).unwrap()),
-            _ => None
+            Some(bytes) => from_utf8(bytes).map_err(|_| {
+                let err = String::from_utf8(bytes.clone()).unwrap_err();
+                BencodeError::InvalidUtf8(err)
+            }),
+            None => Err(BencodeError::UnknownType),
+        }
+    }
+
+
+    /// Encode this value back into its canonical Bencode wire form
+    /// ## Example
+    /// ```rust
+    /// use torcode::bencode::BValue;
+    /// assert_eq!(BValue::BNumber(3).encode(), b"i3e".to_vec());
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            BValue::BNumber(n) => format!("i{}e", n).into_bytes(),
+            BValue::BBytes(bytes) => {
+                let mut result = format!("{}:", bytes.len()).into_bytes();
+                result.extend_from_slice(bytes);
+                result
+            },
+            BValue::BList(list) => {
+                let mut result = vec![b'l'];
+                for value in list {
+                    result.extend(value.encode());
+                }
+                result.push(b'e');
+                result
+            },
+            BValue::BDict(map) => {
+                let mut result = vec![b'd'];
+                for (key, value) in map {
+                    result.extend(BValue::BBytes(key.clone()).encode());
+                    result.extend(value.encode());
+                }
+                result.push(b'e');
+                result
+            },
         }
     }
+
+
+    /// Locates this metainfo dict's `info` entry and re-encodes *only that sub-value* in
+    /// canonical Bencode form
+    ///
+    /// The returned bytes are exactly what BitTorrent trackers and peers hash (SHA-1) to derive
+    /// the info-hash, so the rest of the top-level dict is left untouched and the info dict's
+    /// keys come out sorted (guaranteed by `BDict`'s `BTreeMap`).
+    /// ## Example
+    /// ```rust
+    /// use torcode::bencode::BValue;
+    /// let (_, torrent) = BValue::from_bytes(&b"d4:infod6:lengthi0eee"[..]).unwrap();
+    /// assert_eq!(torrent.info_encoded(), Ok(b"d6:lengthi0ee".to_vec()));
+    /// ```
+    pub fn info_encoded(&self) -> Result<Vec<u8>, BencodeError> {
+        self.get(b"info").map(BValue::encode).ok_or(BencodeError::UnknownType)
+    }
+
+
+    /// Computes the BitTorrent info-hash: the SHA-1 digest of [`info_encoded`](Self::info_encoded)
+    #[cfg(feature = "sha1")]
+    pub fn info_hash_sha1(&self) -> Result<[u8; 20], BencodeError> {
+        use sha1::{Digest, Sha1};
+
+        let encoded = self.info_encoded()?;
+        let mut hasher = Sha1::new();
+        hasher.update(&encoded);
+        Ok(hasher.finalize().into())
+    }
+
+
+    /// Encode this value into its canonical Bencode wire form as a lossy UTF-8 `String`
+    /// ## Example
+    /// ```rust
+    /// use torcode::bencode::BValue;
+    /// assert_eq!(BValue::BNumber(3).to_bencode_string(), "i3e".to_string());
+    /// ```
+    pub fn to_bencode_string(&self) -> String {
+        String::from_utf8_lossy(&self.encode()).into_owned()
+    }
 }
 
 
-fn parse_number(i: &[u8]) -> IResult<&[u8], i64> {
-    let signed_digit = recognize(pair(opt(char('-')), digit1));
-    let parsed_num = map_res(signed_digit, |s: &[u8]| from_utf8(s).unwrap().parse::<i64>());
+/// Backing iterator for `BValue::iter_from_bytes`
+struct ValueStream<'a> {
+    remaining: &'a [u8],
+    done: bool,
+}
+
 
-    terminated(preceded(char('i'), parsed_num), char('e'))(i)
+impl<'a> Iterator for ValueStream<'a> {
+    type Item = Result<BValue, BencodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.remaining.is_empty() {
+            return None;
+        }
+
+        match BValue::from_bytes(self.remaining) {
+            Ok((rest, value)) => {
+                self.remaining = rest;
+                Some(Ok(value))
+            },
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            },
+        }
+    }
 }
 
 
-fn parse_length(i: &[u8]) -> IResult<&[u8], usize> {
-    let len = terminated(digit1, char(':'));
-    map_res(len, |s: &[u8]| from_utf8(s).unwrap().parse::<usize>())(i)
+/// Matches a single literal character, reporting `BencodeError::Expected` on mismatch
+fn expect<'a>(c: char) -> impl Fn(&'a [u8]) -> IResult<&'a [u8], char, PosError<'a>> {
+    move |i: &'a [u8]| char(c)(i).map_err(|e: nom::Err<PosError<'a>>| match e {
+        nom::Err::Incomplete(n) => nom::Err::Incomplete(n),
+        _ => nom::Err::Error(PosError::new(BencodeError::Expected(c), i)),
+    })
 }
 
 
-fn parse_string(i: &[u8]) -> IResult<&[u8], String> {
-    map_res(parse_bytes, String::from_utf8)(i)
+fn parse_value(i: &[u8]) -> IResult<&[u8], BValue, PosError<'_>> {
+    let bnumber = map(parse_number, BValue::BNumber);
+    let bbytes = map(parse_bytes, BValue::BBytes);
+    let blist = map(parse_list, BValue::BList);
+    let bdict = map(parse_dict, BValue::BDict);
+    alt((bnumber, bbytes, blist, bdict))(i)
 }
 
 
-fn parse_bytes(i: &[u8]) -> IResult<&[u8], Vec<u8>> {
+fn parse_number(i: &[u8]) -> IResult<&[u8], i64, PosError<'_>> {
+    let signed_digit = recognize(pair(opt(char('-')), digit1));
+    let as_string = map_res(signed_digit, |s: &[u8]| String::from_utf8(s.to_vec()));
+    let parsed_num = map_res(as_string, |s: String| s.parse::<i64>());
+
+    terminated(preceded(expect('i'), parsed_num), expect('e'))(i)
+}
+
+
+fn parse_length(i: &[u8]) -> IResult<&[u8], usize, PosError<'_>> {
+    let len = terminated(digit1, expect(':'));
+    let as_string = map_res(len, |s: &[u8]| String::from_utf8(s.to_vec()));
+    map_res(as_string, |s: String| s.parse::<usize>())(i)
+}
+
+
+fn parse_bytes(i: &[u8]) -> IResult<&[u8], Vec<u8>, PosError<'_>> {
     let (left, len) = parse_length(i)?;
     let result = take(len);
     map(result, |s: &[u8]| s.to_vec())(left)
 }
 
 
-fn parse_list(i: &[u8]) -> IResult<&[u8], Vec<BValue>> {
-    let values = many1(BValue::from_bytes);
-    preceded(char('l'), terminated(values, char('e')))(i)
+fn parse_list(i: &[u8]) -> IResult<&[u8], Vec<BValue>, PosError<'_>> {
+    let values = many1(parse_value);
+    preceded(expect('l'), terminated(values, expect('e')))(i)
 }
 
 
-fn parse_dict(i: &[u8]) -> IResult<&[u8], HashMap<String, BValue>> {
-    let kv = pair(parse_string, BValue::from_bytes);
+fn parse_dict(i: &[u8]) -> IResult<&[u8], BTreeMap<Vec<u8>, BValue>, PosError<'_>> {
+    let kv = pair(parse_bytes, parse_value);
     let kv = many1(kv);
-    let kv = terminated(preceded(char('d'), kv), char('e'));
+    let kv = terminated(preceded(expect('d'), kv), expect('e'));
     map(kv, |s| s.into_iter().collect())(i)
 }
+
+
+/// `true` if `digits` (an optional `-` followed by one or more decimal digits) has no leading
+/// zero in its magnitude and is not `-0`
+fn is_canonical_int(digits: &[u8]) -> bool {
+    let magnitude = match digits.split_first() {
+        Some((b'-', rest)) => rest,
+        _ => digits,
+    };
+    if digits[0] == b'-' && magnitude == b"0" {
+        return false;
+    }
+    !(magnitude.len() > 1 && magnitude[0] == b'0')
+}
+
+
+fn parse_value_strict(i: &[u8]) -> IResult<&[u8], BValue, PosError<'_>> {
+    let bnumber = map(parse_number_strict, BValue::BNumber);
+    let bbytes = map(parse_bytes_strict, BValue::BBytes);
+    let blist = map(parse_list_strict, BValue::BList);
+    let bdict = map(parse_dict_strict, BValue::BDict);
+    alt((bnumber, bbytes, blist, bdict))(i)
+}
+
+
+fn parse_number_strict(i: &[u8]) -> IResult<&[u8], i64, PosError<'_>> {
+    let (i, _) = expect('i')(i)?;
+    let (i, digits) = recognize(pair(opt(char('-')), digit1))(i)?;
+    if !is_canonical_int(digits) {
+        return Err(nom::Err::Error(PosError::new(BencodeError::LeadingZero, i)));
+    }
+    let (i, _) = expect('e')(i)?;
+
+    let s = String::from_utf8(digits.to_vec()).map_err(|e| nom::Err::Error(PosError::new(BencodeError::InvalidUtf8(e), i)))?;
+    let n = s.parse::<i64>().map_err(|e| nom::Err::Error(PosError::new(BencodeError::InvalidNumber(e), i)))?;
+    Ok((i, n))
+}
+
+
+fn parse_length_strict(i: &[u8]) -> IResult<&[u8], usize, PosError<'_>> {
+    let (i, digits) = terminated(digit1, expect(':'))(i)?;
+    if digits.len() > 1 && digits[0] == b'0' {
+        return Err(nom::Err::Error(PosError::new(BencodeError::LeadingZero, i)));
+    }
+
+    let s = String::from_utf8(digits.to_vec()).map_err(|e| nom::Err::Error(PosError::new(BencodeError::InvalidUtf8(e), i)))?;
+    let n = s.parse::<usize>().map_err(|e| nom::Err::Error(PosError::new(BencodeError::InvalidNumber(e), i)))?;
+    Ok((i, n))
+}
+
+
+fn parse_bytes_strict(i: &[u8]) -> IResult<&[u8], Vec<u8>, PosError<'_>> {
+    let (left, len) = parse_length_strict(i)?;
+    map(take(len), |s: &[u8]| s.to_vec())(left)
+}
+
+
+fn parse_list_strict(i: &[u8]) -> IResult<&[u8], Vec<BValue>, PosError<'_>> {
+    let values = many1(parse_value_strict);
+    preceded(expect('l'), terminated(values, expect('e')))(i)
+}
+
+
+fn parse_dict_strict(i: &[u8]) -> IResult<&[u8], BTreeMap<Vec<u8>, BValue>, PosError<'_>> {
+    let kv = pair(parse_bytes_strict, parse_value_strict);
+    let (i, pairs) = terminated(preceded(expect('d'), many1(kv)), expect('e'))(i)?;
+
+    let mut map = BTreeMap::new();
+    let mut prev_key: Option<Vec<u8>> = None;
+    for (key, value) in pairs {
+        if let Some(prev) = &prev_key {
+            if key <= *prev {
+                return Err(nom::Err::Error(PosError::new(BencodeError::UnsortedKeys, i)));
+            }
+        }
+        prev_key = Some(key.clone());
+        map.insert(key, value);
+    }
+    Ok((i, map))
+}
@@ -0,0 +1,109 @@
+use crate::bencode::{BValue, BencodeError};
+
+
+/// Decodes a parsed `BValue` into a typed value
+///
+/// Decoders compose with [`DecoderExt::then`], so navigating a `.torrent` structure can be
+/// written as `RecordDot("announce").then(Text)` instead of a chain of `get_dict()?.get(...)?`
+/// calls with manual matching.
+pub trait Decoder {
+    type Output;
+
+    fn decode(&self, value: &BValue) -> Result<Self::Output, BencodeError>;
+}
+
+
+/// Asserts the value is a `BBytes` string and decodes it as UTF-8 text
+pub struct Text;
+
+impl Decoder for Text {
+    type Output = String;
+
+    fn decode(&self, value: &BValue) -> Result<Self::Output, BencodeError> {
+        value.get_string().map(str::to_string)
+    }
+}
+
+
+/// Asserts the value is a `BBytes` string and returns its raw bytes
+pub struct Bytes;
+
+impl Decoder for Bytes {
+    type Output = Vec<u8>;
+
+    fn decode(&self, value: &BValue) -> Result<Self::Output, BencodeError> {
+        value.get_bytes().cloned().ok_or(BencodeError::UnknownType)
+    }
+}
+
+
+/// Asserts the value is a `BNumber` and returns it
+pub struct AsNumber;
+
+impl Decoder for AsNumber {
+    type Output = i64;
+
+    fn decode(&self, value: &BValue) -> Result<Self::Output, BencodeError> {
+        value.get_number().copied().ok_or(BencodeError::UnknownType)
+    }
+}
+
+
+/// Looks up `key` in a `BDict` and returns the field's raw `BValue`
+///
+/// Meant to be chained with a typed decoder via [`DecoderExt::then`], e.g.
+/// `RecordDot("announce").then(Text)`.
+pub struct RecordDot(pub &'static str);
+
+impl Decoder for RecordDot {
+    type Output = BValue;
+
+    fn decode(&self, value: &BValue) -> Result<Self::Output, BencodeError> {
+        value.get(self.0.as_bytes()).cloned().ok_or(BencodeError::UnknownType)
+    }
+}
+
+
+/// Succeeds only if the value equals one of an allowed set, returning it unchanged
+pub struct OneOf(pub Vec<BValue>);
+
+impl Decoder for OneOf {
+    type Output = BValue;
+
+    fn decode(&self, value: &BValue) -> Result<Self::Output, BencodeError> {
+        self.0.iter().find(|allowed| *allowed == value)
+            .cloned()
+            .ok_or(BencodeError::UnknownType)
+    }
+}
+
+
+/// Chains two decoders, feeding the first's `BValue` output into the second
+pub struct Then<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> Decoder for Then<A, B>
+where
+    A: Decoder<Output = BValue>,
+    B: Decoder,
+{
+    type Output = B::Output;
+
+    fn decode(&self, value: &BValue) -> Result<Self::Output, BencodeError> {
+        let inner = self.first.decode(value)?;
+        self.second.decode(&inner)
+    }
+}
+
+
+/// Extension trait adding the `.then(...)` chaining syntax to any decoder that outputs a
+/// `BValue` (such as `RecordDot`)
+pub trait DecoderExt: Decoder<Output = BValue> + Sized {
+    fn then<B: Decoder>(self, next: B) -> Then<Self, B> {
+        Then { first: self, second: next }
+    }
+}
+
+impl<T: Decoder<Output = BValue>> DecoderExt for T {}
@@ -1,13 +1,15 @@
 pub mod bencode;
+pub mod decode;
 
-pub use bencode::BValue;
+pub use bencode::{BValue, BencodeError};
 
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
     use super::*;
+    use crate::decode::{Decoder, DecoderExt, Text, AsNumber, RecordDot, OneOf};
 
 
     #[test]
@@ -40,10 +42,197 @@ mod tests {
 
     #[test]
     fn test_parse_dict() {
-        let mut expected: HashMap<String, BValue> = HashMap::new();
-        expected.entry("cow".to_string()).or_insert(BValue::BBytes("moo".as_bytes().to_vec()));
-        expected.entry("spam".to_string()).or_insert(BValue::BBytes("eggs".as_bytes().to_vec()));
+        let mut expected: BTreeMap<Vec<u8>, BValue> = BTreeMap::new();
+        expected.entry(b"cow".to_vec()).or_insert(BValue::BBytes("moo".as_bytes().to_vec()));
+        expected.entry(b"spam".to_vec()).or_insert(BValue::BBytes("eggs".as_bytes().to_vec()));
 
         assert_eq!(BValue::from_bytes(&b"d3:cow3:moo4:spam4:eggse"[..]), Ok((&b""[..], BValue::BDict(expected))));
     }
+
+
+    #[test]
+    fn test_encode_number() {
+        let value = BValue::BNumber(-3228);
+        assert_eq!(value.encode(), b"i-3228e".to_vec());
+        assert_eq!(BValue::from_bytes(&value.encode()), Ok((&b""[..], value)));
+    }
+
+
+    #[test]
+    fn test_encode_bytes() {
+        let value = BValue::BBytes("Hello World!".as_bytes().to_vec());
+        assert_eq!(value.encode(), b"12:Hello World!".to_vec());
+        assert_eq!(BValue::from_bytes(&value.encode()), Ok((&b""[..], value)));
+    }
+
+
+    #[test]
+    fn test_encode_list() {
+        let value = BValue::BList(
+            vec![
+                BValue::BBytes("spam".as_bytes().to_vec()),
+                BValue::BBytes("eggs".as_bytes().to_vec())
+            ]
+        );
+        assert_eq!(value.encode(), b"l4:spam4:eggse".to_vec());
+        assert_eq!(BValue::from_bytes(&value.encode()), Ok((&b""[..], value)));
+    }
+
+
+    #[test]
+    fn test_parse_errors_do_not_panic() {
+        assert_eq!(BValue::from_bytes(&b""[..]), Err(BencodeError::InputTooShort));
+        assert!(matches!(BValue::from_bytes(&b"x"[..]), Err(BencodeError::Expected('i'))));
+        assert!(matches!(BValue::from_bytes(&b"i3"[..]), Err(BencodeError::Expected('e'))));
+    }
+
+
+    #[test]
+    fn test_get_string_invalid_utf8() {
+        let value = BValue::BBytes(vec![0xff, 0xfe]);
+        assert!(matches!(value.get_string(), Err(BencodeError::InvalidUtf8(_))));
+    }
+
+
+    #[test]
+    fn test_strict_rejects_leading_zero() {
+        assert_eq!(BValue::from_bytes_strict(&b"i03e"[..]), Err(BencodeError::LeadingZero));
+        assert_eq!(BValue::from_bytes_strict(&b"i-0e"[..]), Err(BencodeError::LeadingZero));
+        assert_eq!(BValue::from_bytes_strict(&b"03:abc"[..]), Err(BencodeError::LeadingZero));
+        assert_eq!(BValue::from_bytes_strict(&b"i0e"[..]), Ok((&b""[..], BValue::BNumber(0))));
+    }
+
+
+    #[test]
+    fn test_strict_rejects_unsorted_keys() {
+        assert_eq!(
+            BValue::from_bytes_strict(&b"d4:spam4:eggs3:cow3:mooe"[..]),
+            Err(BencodeError::UnsortedKeys)
+        );
+        assert_eq!(
+            BValue::from_bytes_strict(&b"d3:cow3:moo3:cow3:mooe"[..]),
+            Err(BencodeError::UnsortedKeys)
+        );
+        assert!(BValue::from_bytes_strict(&b"d3:cow3:moo4:spam4:eggse"[..]).is_ok());
+    }
+
+
+    #[test]
+    fn test_strict_rejects_unsorted_info_dict_keys() {
+        assert_eq!(
+            BValue::from_bytes_strict(&b"d4:infod4:name4:test6:lengthi0eee"[..]),
+            Err(BencodeError::UnsortedKeys)
+        );
+        assert!(BValue::from_bytes_strict(&b"d4:infod6:lengthi0e4:name4:testee"[..]).is_ok());
+    }
+
+
+    #[test]
+    fn test_encode_dict() {
+        let mut map: BTreeMap<Vec<u8>, BValue> = BTreeMap::new();
+        map.entry(b"cow".to_vec()).or_insert(BValue::BBytes("moo".as_bytes().to_vec()));
+        map.entry(b"spam".to_vec()).or_insert(BValue::BBytes("eggs".as_bytes().to_vec()));
+        let value = BValue::BDict(map);
+
+        let encoded = value.encode();
+        assert_eq!(encoded, b"d3:cow3:moo4:spam4:eggse".to_vec());
+        assert_eq!(BValue::from_bytes(&encoded), Ok((&b""[..], value)));
+    }
+
+
+    #[test]
+    fn test_iter_from_bytes() {
+        let values: Vec<_> = BValue::iter_from_bytes(&b"i1e4:spami2e"[..]).collect();
+        assert_eq!(
+            values,
+            vec![
+                Ok(BValue::BNumber(1)),
+                Ok(BValue::BBytes("spam".as_bytes().to_vec())),
+                Ok(BValue::BNumber(2)),
+            ]
+        );
+    }
+
+
+    #[test]
+    fn test_iter_from_bytes_empty() {
+        let values: Vec<_> = BValue::iter_from_bytes(&b""[..]).collect();
+        assert_eq!(values, Vec::new());
+    }
+
+
+    #[test]
+    fn test_iter_from_bytes_stops_on_error() {
+        let values: Vec<_> = BValue::iter_from_bytes(&b"i1ei2"[..]).collect();
+        assert_eq!(values, vec![Ok(BValue::BNumber(1)), Err(BencodeError::Expected('e'))]);
+    }
+
+
+    #[test]
+    fn test_decoder_record_dot_then_text() {
+        let (_, torrent) = BValue::from_bytes(&b"d8:announce19:http://tracker.com/e"[..]).unwrap();
+        let announce = RecordDot("announce").then(Text).decode(&torrent).unwrap();
+        assert_eq!(announce, "http://tracker.com/".to_string());
+    }
+
+
+    #[test]
+    fn test_decoder_as_number() {
+        let (_, value) = BValue::from_bytes(&b"i42e"[..]).unwrap();
+        assert_eq!(AsNumber.decode(&value), Ok(42));
+    }
+
+
+    #[test]
+    fn test_decoder_missing_field() {
+        let (_, torrent) = BValue::from_bytes(&b"d3:cow3:mooe"[..]).unwrap();
+        assert_eq!(RecordDot("announce").then(Text).decode(&torrent), Err(BencodeError::UnknownType));
+    }
+
+
+    #[test]
+    fn test_decoder_one_of() {
+        let allowed = OneOf(vec![BValue::BNumber(1), BValue::BNumber(2)]);
+        assert_eq!(allowed.decode(&BValue::BNumber(2)), Ok(BValue::BNumber(2)));
+        assert_eq!(allowed.decode(&BValue::BNumber(3)), Err(BencodeError::UnknownType));
+    }
+
+
+    #[test]
+    fn test_get() {
+        let (_, value) = BValue::from_bytes(&b"d3:cow3:mooe"[..]).unwrap();
+        assert_eq!(value.get(b"cow"), Some(&BValue::BBytes("moo".as_bytes().to_vec())));
+        assert_eq!(value.get(b"missing"), None);
+    }
+
+
+    #[test]
+    fn test_info_encoded() {
+        let (_, torrent) = BValue::from_bytes(
+            &b"d8:announce3:foo4:infod6:lengthi0e4:name4:teste7:comment3:baze"[..]
+        ).unwrap();
+        assert_eq!(torrent.info_encoded(), Ok(b"d6:lengthi0e4:name4:teste".to_vec()));
+    }
+
+
+    #[test]
+    fn test_info_encoded_missing_info() {
+        let (_, torrent) = BValue::from_bytes(&b"d8:announce3:fooe"[..]).unwrap();
+        assert_eq!(torrent.info_encoded(), Err(BencodeError::UnknownType));
+    }
+
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_info_hash_sha1() {
+        let (_, torrent) = BValue::from_bytes(
+            &b"d8:announce3:foo4:infod6:lengthi0e4:name4:teste7:comment3:baze"[..]
+        ).unwrap();
+        // sha1sum of the info_encoded fixture above (b"d6:lengthi0e4:name4:teste")
+        let expected: [u8; 20] = [
+            0xa1, 0xb2, 0x9a, 0xff, 0x5c, 0xd1, 0xc5, 0xc1, 0x30, 0x41,
+            0xbd, 0x57, 0x2a, 0xc2, 0x73, 0x2f, 0x74, 0xd4, 0x86, 0xb6,
+        ];
+        assert_eq!(torrent.info_hash_sha1(), Ok(expected));
+    }
 }